@@ -1,16 +1,50 @@
-use landlock::{
-    path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetError,
-    RulesetStatus, ABI,
-};
+mod sandbox;
+
+use landlock::{CompatLevel, RulesetStatus};
+use sandbox::{Outcome, Policy, Sandbox};
 use std::env;
 use std::path::PathBuf;
 use std::process::{Command, ExitStatus};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    // The wrapper's own leading flags are consumed here, never an
+    // occurrence later in argv — those belong to the wrapped command (e.g.
+    // `devenv-sandbox cargo publish --dry-run` must still pass `--dry-run`
+    // through to cargo). Each also has an environment-variable equivalent
+    // (DEVENV_SANDBOX_DRYRUN, _VERBOSE, _CONFIG) for use outside a shell
+    // that can pass flags, e.g. a devenv.nix-generated wrapper.
+    let mut dry_run_flag = false;
+    let mut verbose_flag = false;
+    let mut config_flag = None;
+    loop {
+        match args.get(1).map(String::as_str) {
+            Some("--dry-run") => {
+                dry_run_flag = true;
+                args.remove(1);
+            }
+            Some("--verbose") => {
+                verbose_flag = true;
+                args.remove(1);
+            }
+            Some("--config") => {
+                args.remove(1);
+                if args.len() < 2 {
+                    eprintln!("--config requires a path argument");
+                    std::process::exit(1);
+                }
+                config_flag = Some(args.remove(1));
+            }
+            _ => break,
+        }
+    }
 
     if args.len() < 2 {
-        eprintln!("Usage: {} <command> [args...]", args[0]);
+        eprintln!(
+            "Usage: {} [--dry-run] [--verbose] [--config <path>] <command> [args...]",
+            args[0]
+        );
         std::process::exit(1);
     }
 
@@ -22,7 +56,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         }
     };
-    
+
     let runtime_dir = match env::var("XDG_RUNTIME_DIR") {
         Ok(path) => path,
         Err(_) => {
@@ -30,7 +64,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         }
     };
-    
+
     // TODO: make sure that the user cannot modify this within the shell
     let home_dir = match env::var("HOME") {
         Ok(path) => path,
@@ -47,14 +81,54 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
-    // Set up landlock sandboxing
-    match setup_landlock_sandbox(&devenv_root, &runtime_dir, &home_dir) {
-        Ok(status) => match status {
+    // Set up landlock sandboxing. Sandbox::new() already honors
+    // DEVENV_SANDBOX_DRYRUN/_VERBOSE from the environment; the CLI flags
+    // only need to force them on, never off.
+    let policy = Policy::from_env(&devenv_root, &runtime_dir, &home_dir);
+    let mut sandbox = Sandbox::from_policy(policy);
+
+    let config_path = config_flag.or_else(|| env::var(sandbox::SANDBOX_CONFIG_VAR).ok());
+    if let Some(config_path) = config_path {
+        let config = match std::fs::read_to_string(&config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to read sandbox config {}: {}", config_path, e);
+                std::process::exit(1);
+            }
+        };
+        let extra = Policy::from_config(&config);
+        for path in extra.ro_paths {
+            sandbox = sandbox.add_ro_path(path);
+        }
+        for path in extra.rw_paths {
+            sandbox = sandbox.add_rw_path(path);
+        }
+        sandbox = sandbox.with_net_ports(extra.bind_ports, extra.connect_ports);
+    }
+
+    if dry_run_flag {
+        sandbox = sandbox.dry_run(true);
+    }
+    if verbose_flag {
+        sandbox = sandbox.verbose(true);
+    }
+    if sandbox::env_flag(sandbox::SANDBOX_STRICT_VAR) {
+        sandbox = sandbox.abi_compat_level(CompatLevel::HardRequirement);
+    }
+
+    match sandbox.enforce() {
+        Ok((Outcome::DryRun, _abi)) => {
+            // Sandbox::enforce() already printed the resolved policy.
+        }
+        Ok((Outcome::Enforced(status), abi)) => match status {
             RulesetStatus::FullyEnforced => {
-                println!("Landlock: Fully sandboxed to {}", devenv_root)
+                println!("Landlock: Fully sandboxed to {} (ABI {:?})", devenv_root, abi)
             }
             RulesetStatus::PartiallyEnforced => {
-                println!("Landlock: Partially sandboxed to {}", devenv_root)
+                println!(
+                    "Landlock: Partially sandboxed to {} (ABI {:?})",
+                    devenv_root, abi
+                )
             }
             RulesetStatus::NotEnforced => {
                 println!("Landlock: Not sandboxed! Please update your kernel.")
@@ -82,39 +156,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::process::exit(status.code().unwrap_or(1));
 }
 
-fn setup_landlock_sandbox(devenv_root: &str, runtime_dir: &str, home: &str) -> Result<RulesetStatus, RulesetError> {
-    let abi = ABI::V2;
-
-    // Create a ruleset that only allows access to the DEVENV_ROOT directory
-    let status = Ruleset::default()
-        .handle_access(AccessFs::from_all(abi))?
-        .create()?
-        .add_rules(path_beneath_rules(&[
-            devenv_root, 
-            runtime_dir, 
-            // for Python uv
-            &(home.to_owned()+"/.cache/uv"), 
-            // error without: GC_push_all_stacks: pthread_getattr_np failed!
-            "/proc", // TODO: limit further. I was too lazy.
-            // process-compose logs here
-            "/tmp", // TODO: can we limit this further?
-            // required by process-compose for tui
-            "/dev/tty", 
-            // for redirecting output into /dev/null
-            "/dev/null",
-        ], AccessFs::from_all(abi)))?
-        .add_rules(path_beneath_rules(&[
-            "/nix", 
-            "/proc/stat",
-        ], AccessFs::from_read(abi)))?
-        .restrict_self()?;
-
-    Ok(status.ruleset)
-}
-
 fn execute_command(command: &str, args: &[String]) -> Result<ExitStatus, std::io::Error> {
     let status = Command::new(command).args(args).status()?;
 
     Ok(status)
 }
-
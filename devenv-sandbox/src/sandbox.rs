@@ -0,0 +1,418 @@
+use landlock::{
+    net_port_rules, path_beneath_rules, Access, AccessFs, AccessNet, CompatLevel, Compatible,
+    Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetError, RulesetStatus, ABI,
+};
+use std::env;
+use std::path::PathBuf;
+
+// Colon-separated list of additional paths, following the kernel sandboxer's
+// LL_FS_RO/LL_FS_RW convention. Lets a project's devenv.nix grant itself extra
+// directories without recompiling the wrapper.
+const SANDBOX_FS_RO_VAR: &str = "DEVENV_SANDBOX_FS_RO";
+const SANDBOX_FS_RW_VAR: &str = "DEVENV_SANDBOX_FS_RW";
+
+// Colon-separated list of TCP ports to allow, e.g. "5432:6379". Only takes
+// effect on kernels new enough to support Landlock's network access rights
+// (ABI v4); older kernels fall back to filesystem-only confinement.
+const SANDBOX_NET_BIND_VAR: &str = "DEVENV_SANDBOX_NET_BIND";
+const SANDBOX_NET_CONNECT_VAR: &str = "DEVENV_SANDBOX_NET_CONNECT";
+
+/// Set to build and print the resolved ruleset without calling
+/// `restrict_self()`, so the command runs unconfined. See [`Sandbox::dry_run`].
+pub const SANDBOX_DRYRUN_VAR: &str = "DEVENV_SANDBOX_DRYRUN";
+/// Set to log every path hierarchy and the access bits granted to it. See
+/// [`Sandbox::verbose`].
+pub const SANDBOX_VERBOSE_VAR: &str = "DEVENV_SANDBOX_VERBOSE";
+/// Path to a config file parsed with [`Policy::from_config`] and merged into
+/// the wrapper's default policy. See `--config` in `main`.
+pub const SANDBOX_CONFIG_VAR: &str = "DEVENV_SANDBOX_CONFIG";
+/// Set to require every requested access right to actually be available on
+/// the running kernel, erroring out instead of silently degrading. See
+/// [`Sandbox::abi_compat_level`].
+pub const SANDBOX_STRICT_VAR: &str = "DEVENV_SANDBOX_STRICT";
+
+pub(crate) fn env_flag(var: &str) -> bool {
+    matches!(env::var(var), Ok(value) if value != "0" && !value.is_empty())
+}
+
+/// The declarative set of paths and ports a [`Sandbox`] should allow, either
+/// assembled from the environment or built up directly by a caller that
+/// already knows its own policy.
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    pub ro_paths: Vec<String>,
+    pub rw_paths: Vec<String>,
+    pub bind_ports: Vec<u16>,
+    pub connect_ports: Vec<u16>,
+}
+
+impl Policy {
+    /// The wrapper's default policy: the paths every devenv shell needs
+    /// (`DEVENV_ROOT`, the runtime dir, `/nix`, ...), extended with whatever
+    /// `DEVENV_SANDBOX_FS_RO`/`_RW`/`_NET_BIND`/`_NET_CONNECT` add on top.
+    pub fn from_env(devenv_root: &str, runtime_dir: &str, home: &str) -> Self {
+        let mut rw_paths = vec![
+            devenv_root.to_owned(),
+            runtime_dir.to_owned(),
+            // for Python uv
+            home.to_owned() + "/.cache/uv",
+            // error without: GC_push_all_stacks: pthread_getattr_np failed!
+            "/proc".to_owned(), // TODO: limit further. I was too lazy.
+            // process-compose logs here
+            "/tmp".to_owned(), // TODO: can we limit this further?
+            // required by process-compose for tui
+            "/dev/tty".to_owned(),
+            // for redirecting output into /dev/null
+            "/dev/null".to_owned(),
+        ];
+        rw_paths.extend(paths_from_env(SANDBOX_FS_RW_VAR));
+
+        let mut ro_paths = vec!["/nix".to_owned(), "/proc/stat".to_owned()];
+        ro_paths.extend(paths_from_env(SANDBOX_FS_RO_VAR));
+
+        Policy {
+            ro_paths,
+            rw_paths,
+            bind_ports: ports_from_env(SANDBOX_NET_BIND_VAR),
+            connect_ports: ports_from_env(SANDBOX_NET_CONNECT_VAR),
+        }
+    }
+
+    /// Parses a policy out of a small config format: one `key=value` line
+    /// per rule, where `value` is colon-separated like the
+    /// `DEVENV_SANDBOX_*` env vars. Recognized keys are `ro`, `rw`, `bind`
+    /// and `connect`; blank lines, `#` comments and unknown keys are
+    /// ignored. The returned policy holds only what the config lists, without
+    /// the hardcoded defaults from [`Policy::from_env`]; the wrapper's `main`
+    /// adds its paths and ports on top of that default policy.
+    ///
+    /// ```ignore
+    /// let policy = Policy::from_config("ro=/nix\nrw=/tmp:/devenv\nbind=8080\n");
+    /// assert_eq!(policy.ro_paths, vec!["/nix".to_owned()]);
+    /// assert_eq!(policy.bind_ports, vec![8080]);
+    /// ```
+    pub fn from_config(config: &str) -> Self {
+        let mut policy = Policy::default();
+
+        for line in config.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let values = value.split(':').filter(|v| !v.is_empty());
+
+            match key.trim() {
+                "ro" => policy.ro_paths.extend(values.map(String::from)),
+                "rw" => policy.rw_paths.extend(values.map(String::from)),
+                "bind" => policy.bind_ports.extend(values.filter_map(|v| v.parse().ok())),
+                "connect" => policy
+                    .connect_ports
+                    .extend(values.filter_map(|v| v.parse().ok())),
+                _ => {}
+            }
+        }
+
+        policy
+    }
+}
+
+fn paths_from_env(var: &str) -> Vec<String> {
+    match env::var(var) {
+        Ok(value) => value
+            .split(':')
+            .filter(|path| !path.is_empty())
+            .map(String::from)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn ports_from_env(var: &str) -> Vec<u16> {
+    match env::var(var) {
+        Ok(value) => value
+            .split(':')
+            .filter(|port| !port.is_empty())
+            .filter_map(|port| match port.parse() {
+                Ok(port) => Some(port),
+                Err(_) => {
+                    eprintln!("{}: ignoring invalid port {:?}", var, port);
+                    None
+                }
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Paths that exist on (almost) every system, filtered down to whatever is
+// actually present before being handed to Landlock. The sandboxer itself
+// silently drops missing hierarchies, so we do the same.
+fn existing_paths(paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .filter(|path| PathBuf::from(path).exists())
+        .cloned()
+        .collect()
+}
+
+fn refer_supported(abi: ABI) -> bool {
+    abi >= ABI::V2
+}
+
+/// Builds and enforces a Landlock ruleset from a [`Policy`].
+///
+/// This lives in its own module so the policy logic can be unit-tested in
+/// isolation from `main`'s argv/env handling; it is not (yet) exposed as a
+/// library crate other devenv binaries can depend on.
+///
+/// ```ignore
+/// Sandbox::new()
+///     .add_rw_path("/tmp")
+///     .add_ro_path("/nix")
+///     .enforce()
+///     .unwrap();
+/// ```
+pub struct Sandbox {
+    policy: Policy,
+    compat: CompatLevel,
+    dry_run: bool,
+    verbose: bool,
+}
+
+/// What [`Sandbox::enforce`] actually did with the built ruleset.
+#[derive(Debug)]
+pub enum Outcome {
+    /// `restrict_self()` was called; the wrapped `RulesetStatus` says how
+    /// much of the policy the kernel was able to enforce.
+    Enforced(RulesetStatus),
+    /// The ruleset was built and validated but `restrict_self()` was
+    /// skipped, per [`Sandbox::dry_run`]. The command will run unconfined.
+    DryRun,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Sandbox {
+            policy: Policy::default(),
+            compat: CompatLevel::BestEffort,
+            dry_run: env_flag(SANDBOX_DRYRUN_VAR),
+            verbose: env_flag(SANDBOX_VERBOSE_VAR),
+        }
+    }
+
+    pub fn from_policy(policy: Policy) -> Self {
+        Sandbox {
+            policy,
+            ..Self::new()
+        }
+    }
+
+    pub fn add_ro_path(mut self, path: impl Into<String>) -> Self {
+        self.policy.ro_paths.push(path.into());
+        self
+    }
+
+    pub fn add_rw_path(mut self, path: impl Into<String>) -> Self {
+        self.policy.rw_paths.push(path.into());
+        self
+    }
+
+    pub fn with_net_ports(mut self, bind_ports: Vec<u16>, connect_ports: Vec<u16>) -> Self {
+        self.policy.bind_ports.extend(bind_ports);
+        self.policy.connect_ports.extend(connect_ports);
+        self
+    }
+
+    pub fn abi_compat_level(mut self, compat: CompatLevel) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    /// When set, `enforce()` builds and validates the full ruleset but skips
+    /// `restrict_self()`, so the command runs unconfined. Lets users iterate
+    /// on an allowlist (see the printed report from [`Sandbox::verbose`])
+    /// before turning enforcement on.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// When set, `enforce()` logs every path hierarchy and the access bits
+    /// granted to it, plus the negotiated ABI, before enforcing (or, in
+    /// combination with [`Sandbox::dry_run`], instead of enforcing).
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Negotiates the best supported ABI, builds the ruleset described by
+    /// this sandbox's policy and, unless [`Sandbox::dry_run`] is set, calls
+    /// `restrict_self()`. Returns what was done and the ABI that was used.
+    pub fn enforce(&self) -> Result<(Outcome, ABI), RulesetError> {
+        let abi = ABI::new_current();
+
+        // LANDLOCK_ACCESS_FS_REFER (needed to rename/link across different
+        // allowed hierarchies, e.g. an atomic write into DEVENV_ROOT) behaves
+        // differently from every other access right: the kernel hard-denies
+        // it under ABI v1 even in best-effort mode, instead of silently
+        // dropping the bit. So unlike the rest of the RW access set, we only
+        // request it once the negotiated ABI is new enough to support it.
+        let rw_access = if refer_supported(abi) {
+            AccessFs::from_all(abi) | AccessFs::Refer
+        } else {
+            AccessFs::from_all(abi)
+        };
+
+        let rw_paths = existing_paths(&self.policy.rw_paths);
+        let ro_paths = existing_paths(&self.policy.ro_paths);
+
+        if self.verbose || self.dry_run {
+            self.log_policy(abi, &rw_paths, &ro_paths, rw_access);
+        }
+
+        // Net access rights are a no-op (via best-effort mode) on kernels
+        // that predate Landlock ABI v4, so a misbehaving build script is
+        // confined to the filesystem rules there instead of failing
+        // outright.
+        let created = Ruleset::default()
+            .set_compatibility(self.compat)
+            .handle_access(rw_access)?
+            .handle_access(AccessNet::from_all(abi))?
+            .create()?
+            .add_rules(path_beneath_rules(&rw_paths, rw_access))?
+            .add_rules(path_beneath_rules(&ro_paths, AccessFs::from_read(abi)))?
+            .add_rules(net_port_rules(&self.policy.bind_ports, AccessNet::BindTcp))?
+            .add_rules(net_port_rules(
+                &self.policy.connect_ports,
+                AccessNet::ConnectTcp,
+            ))?;
+
+        // This has to fire regardless of dry-run: surfacing what a policy
+        // would restrict (without enforcing it) is the whole point of
+        // dry-run, and a missing Refer warning is exactly the kind of
+        // "EACCES somewhere deep" confusion it exists to prevent.
+        if !refer_supported(abi) {
+            eprintln!(
+                "Landlock: ABI {:?} does not support LANDLOCK_ACCESS_FS_REFER; \
+                 renaming or linking a file between two different allowed \
+                 directories will fail with EACCES.",
+                abi
+            );
+        }
+
+        if self.dry_run {
+            println!("Landlock: dry-run (ABI {:?}); command will run unconfined", abi);
+            return Ok((Outcome::DryRun, abi));
+        }
+
+        let status = created.restrict_self()?;
+
+        Ok((Outcome::Enforced(status.ruleset), abi))
+    }
+
+    fn log_policy(&self, abi: ABI, rw_paths: &[String], ro_paths: &[String], rw_access: AccessFs) {
+        eprintln!("Landlock: negotiated ABI {:?}", abi);
+        for path in rw_paths {
+            eprintln!("Landlock: rw {} ({:?})", path, rw_access);
+        }
+        for path in ro_paths {
+            eprintln!("Landlock: ro {} ({:?})", path, AccessFs::from_read(abi));
+        }
+        for port in &self.policy.bind_ports {
+            eprintln!("Landlock: bind tcp/{}", port);
+        }
+        for port in &self.policy.connect_ports {
+            eprintln!("Landlock: connect tcp/{}", port);
+        }
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_env_includes_defaults_and_env_overrides() {
+        env::set_var("DEVENV_SANDBOX_FS_RO", "/extra/ro");
+        env::set_var("DEVENV_SANDBOX_FS_RW", "/extra/rw");
+        env::set_var("DEVENV_SANDBOX_NET_BIND", "8080:8443");
+        env::set_var("DEVENV_SANDBOX_NET_CONNECT", "443");
+
+        let policy = Policy::from_env("/devenv", "/run/user/1000", "/home/user");
+
+        assert!(policy.rw_paths.contains(&"/devenv".to_owned()));
+        assert!(policy.rw_paths.contains(&"/extra/rw".to_owned()));
+        assert!(policy.ro_paths.contains(&"/nix".to_owned()));
+        assert!(policy.ro_paths.contains(&"/extra/ro".to_owned()));
+        assert_eq!(policy.bind_ports, vec![8080, 8443]);
+        assert_eq!(policy.connect_ports, vec![443]);
+
+        env::remove_var("DEVENV_SANDBOX_FS_RO");
+        env::remove_var("DEVENV_SANDBOX_FS_RW");
+        env::remove_var("DEVENV_SANDBOX_NET_BIND");
+        env::remove_var("DEVENV_SANDBOX_NET_CONNECT");
+    }
+
+    #[test]
+    fn from_config_parses_keys_and_ignores_comments() {
+        let policy = Policy::from_config(
+            "# a comment\n\nro=/nix:/proc/stat\nrw=/tmp\nbind=8080:8443\nconnect=443\nbogus=nope\n",
+        );
+
+        assert_eq!(policy.ro_paths, vec!["/nix".to_owned(), "/proc/stat".to_owned()]);
+        assert_eq!(policy.rw_paths, vec!["/tmp".to_owned()]);
+        assert_eq!(policy.bind_ports, vec![8080, 8443]);
+        assert_eq!(policy.connect_ports, vec![443]);
+    }
+
+    #[test]
+    fn existing_paths_drops_missing_hierarchies() {
+        let paths = vec!["/nonexistent/path/for/sure".to_owned(), "/tmp".to_owned()];
+        assert_eq!(existing_paths(&paths), vec!["/tmp".to_owned()]);
+    }
+
+    #[test]
+    fn refer_is_gated_on_abi_v2() {
+        assert!(!refer_supported(ABI::V1));
+        assert!(refer_supported(ABI::V2));
+    }
+
+    #[test]
+    fn dry_run_and_verbose_default_from_env() {
+        env::set_var(SANDBOX_DRYRUN_VAR, "1");
+        env::set_var(SANDBOX_VERBOSE_VAR, "1");
+
+        let sandbox = Sandbox::new();
+        assert!(sandbox.dry_run);
+        assert!(sandbox.verbose);
+
+        env::remove_var(SANDBOX_DRYRUN_VAR);
+        env::remove_var(SANDBOX_VERBOSE_VAR);
+
+        let sandbox = Sandbox::new();
+        assert!(!sandbox.dry_run);
+        assert!(!sandbox.verbose);
+    }
+
+    #[test]
+    fn builder_accumulates_paths_and_ports() {
+        let sandbox = Sandbox::new()
+            .add_ro_path("/nix")
+            .add_rw_path("/tmp")
+            .with_net_ports(vec![8080], vec![443]);
+
+        assert_eq!(sandbox.policy.ro_paths, vec!["/nix".to_owned()]);
+        assert_eq!(sandbox.policy.rw_paths, vec!["/tmp".to_owned()]);
+        assert_eq!(sandbox.policy.bind_ports, vec![8080]);
+        assert_eq!(sandbox.policy.connect_ports, vec![443]);
+    }
+}
@@ -0,0 +1,64 @@
+// Integration tests for the devenv-sandbox wrapper binary. These launch the
+// built binary as a child process (rather than calling the Landlock API
+// in-process, which a test harness can't safely re-enter once restricted)
+// and assert it confines reads to the configured allowlist. Writes are not
+// exercised here.
+
+use std::fs;
+use std::process::{Command, Output};
+
+fn devenv_sandbox(devenv_root: &str) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_devenv-sandbox"));
+    cmd.env("DEVENV_ROOT", devenv_root);
+    cmd.env("XDG_RUNTIME_DIR", std::env::temp_dir());
+    cmd.env("HOME", std::env::var("HOME").unwrap_or_default());
+    cmd
+}
+
+// The wrapper prints "Landlock: Not sandboxed!" and still runs the command
+// unconfined when the kernel doesn't support Landlock at all (older kernels,
+// many containerized CI runners). Tests that rely on enforcement need to
+// recognize that case rather than assume restrictions always apply.
+fn landlock_enforced(output: &Output) -> bool {
+    !String::from_utf8_lossy(&output.stdout).contains("Landlock: Not sandboxed!")
+}
+
+#[test]
+fn allows_reads_inside_devenv_root() {
+    let root = std::env::temp_dir().join(format!("devenv-sandbox-test-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    let file = root.join("allowed.txt");
+    fs::write(&file, "ok").unwrap();
+
+    let status = devenv_sandbox(root.to_str().unwrap())
+        .args(["cat", file.to_str().unwrap()])
+        .status()
+        .expect("failed to run devenv-sandbox");
+
+    assert!(status.success());
+
+    fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn denies_reads_outside_the_allowlist() {
+    let root = std::env::temp_dir().join(format!("devenv-sandbox-test-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+
+    let outside = std::path::Path::new("/etc/hostname");
+
+    let output = devenv_sandbox(root.to_str().unwrap())
+        .args(["cat", outside.to_str().unwrap()])
+        .output()
+        .expect("failed to run devenv-sandbox");
+
+    if !landlock_enforced(&output) {
+        eprintln!("skipping: Landlock is not supported on this kernel");
+        fs::remove_dir_all(&root).ok();
+        return;
+    }
+
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(&root).ok();
+}